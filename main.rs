@@ -1,16 +1,17 @@
+mod http_date;
 mod message_parser;
 use server::ThreadPool;
 use std::{
     fs,
     net::{TcpListener, TcpStream},
-    io::{self, prelude::*, BufReader, Read, Write, ErrorKind},
+    io::{self, prelude::*, Read, Write, SeekFrom},
+    path::Path,
     thread,
     time::Duration,
     collections::HashMap,
-    process::Command,
-    sync::{mpsc, Arc, Mutex},
+    process::{Command, Stdio},
 };
-use message_parser::MessageParser;
+use message_parser::{MessageParser, ParseError, Request};
 
 #[derive(Clone)]
 struct ServerConfig {
@@ -21,46 +22,107 @@ struct ServerConfig {
     max_body_size: usize,
     allowed_methods: Vec<String>,
     default_file: String,
+    keep_alive_timeout: Duration,
+    autoindex: bool,
+    cgi_interpreters: HashMap<String, String>,
 }
 
+/// Serves one or more requests off the same socket. Loops until the client
+/// asks to close, the protocol default says to close, or `parse_request`
+/// stalls past `keep_alive_timeout` (a slow/idle client gets a 408 and the
+/// socket is dropped).
 fn handle_connection(mut stream: TcpStream, config: &ServerConfig) -> io::Result<()> {
-    match MessageParser::parse_request(&mut stream, config.max_body_size) {
-        Ok(request) => {
-            let host = request.headers.get("Host").cloned().unwrap_or_default();
-            if !host.starts_with(&config.hostname) {
-                return send_error_response(&mut stream, 404, config);
-            }
+    loop {
+        stream.set_read_timeout(Some(config.keep_alive_timeout))?;
 
-            if !config.allowed_methods.contains(&request.method) {
-                return send_error_response(&mut stream, 405, config);
+        let request = match MessageParser::parse_request(&mut stream, config.max_body_size) {
+            Ok(request) => request,
+            Err(ParseError::ConnectionClosed) => return Ok(()),
+            Err(ParseError::Timeout) => return send_error_response(&mut stream, 408, config),
+            Err(ParseError::PayloadTooLarge) => return send_error_response(&mut stream, 413, config),
+            Err(e) => {
+                eprintln!("Error parsing request: {}", e);
+                return send_error_response(&mut stream, 400, config);
             }
+        };
 
-            let (status_line, response_body) = match request.method.as_str() {
-                "GET" => handle_get(&request.path, config),
-                "POST" => handle_post(&request.path, &request.body, config),
-                "DELETE" => handle_delete(&request.path, config),
-                _ => (
-                    "HTTP/1.1 405 Method Not Allowed".to_string(), 
-                    "Method not allowed".to_string()
-                ),
-            };
+        let host = request.headers.get("Host").cloned().unwrap_or_default();
+        if !host.starts_with(&config.hostname) {
+            return send_error_response(&mut stream, 404, config);
+        }
 
-            let contents = fs::read_to_string(&request.path).unwrap_or_else(|_| response_body);
-            let response = format!(
-                "{status_line}\r\nContent-Length: {}\r\n\r\n{}", 
-                contents.len(), 
-                contents
-            );
-            stream.write_all(response.as_bytes())?;
-            stream.flush()?;
+        if !config.allowed_methods.contains(&request.method) {
+            return send_error_response(&mut stream, 405, config);
+        }
+
+        let persist = client_wants_keep_alive(&request.version, &request.headers);
+        let connection_header = if persist {
+            format!(
+                "Connection: keep-alive\r\nKeep-Alive: timeout={}",
+                config.keep_alive_timeout.as_secs()
+            )
+        } else {
+            "Connection: close".to_string()
+        };
+
+        if request.path.starts_with("/cgi-bin/") {
+            handle_cgi(&mut stream, &request, &connection_header, config)?;
+            if !persist {
+                return Ok(());
+            }
+            continue;
         }
-        Err(e) => {
-            eprintln!("Error parsing request: {}", e);
-            send_error_response(&mut stream, 400, config)?;
+
+        if request.method == "GET" {
+            handle_get(&mut stream, &request.path, &request.headers, &connection_header, config)?;
+            if !persist {
+                return Ok(());
+            }
+            continue;
+        }
+
+        let resolved_path = match resolve_path(&config.root_dir, &request.path) {
+            Some(path) => path,
+            None => {
+                write_403(&mut stream, &connection_header, config)?;
+                if !persist {
+                    return Ok(());
+                }
+                continue;
+            }
+        };
+
+        let (status_line, response_body) = match request.method.as_str() {
+            "POST" => handle_post(&resolved_path, &request.body, config),
+            "DELETE" => handle_delete(&resolved_path, config),
+            _ => (
+                "HTTP/1.1 405 Method Not Allowed".to_string(),
+                "Method not allowed".to_string()
+            ),
+        };
+
+        let response = format!(
+            "{status_line}\r\n{connection_header}\r\nContent-Length: {}\r\n\r\n{}",
+            response_body.len(),
+            response_body
+        );
+        stream.write_all(response.as_bytes())?;
+        stream.flush()?;
+
+        if !persist {
+            return Ok(());
         }
     }
+}
 
-    Ok(())
+/// HTTP/1.1 defaults to persistent connections, HTTP/1.0 defaults to closing;
+/// an explicit `Connection` header always overrides the version default.
+fn client_wants_keep_alive(version: &str, headers: &HashMap<String, String>) -> bool {
+    match headers.get("Connection").map(|v| v.to_ascii_lowercase()) {
+        Some(value) if value == "close" => false,
+        Some(value) if value == "keep-alive" => true,
+        _ => version == "HTTP/1.1",
+    }
 }
 
 fn send_error_response(stream: &mut TcpStream, status_code: u16, config: &ServerConfig) -> io::Result<()> {
@@ -68,11 +130,13 @@ fn send_error_response(stream: &mut TcpStream, status_code: u16, config: &Server
         .cloned()
         .unwrap_or_else(|| format!("Error {}", status_code));
     let response = format!(
-        "HTTP/1.1 {status_code} {}\r\nContent-Length: {}\r\n\r\n{}", 
+        "HTTP/1.1 {status_code} {}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
         match status_code {
             400 => "Bad Request",
+            403 => "Forbidden",
             404 => "Not Found",
             405 => "Method Not Allowed",
+            408 => "Request Timeout",
             413 => "Payload Too Large",
             _ => "Internal Server Error",
         },
@@ -85,43 +149,498 @@ fn send_error_response(stream: &mut TcpStream, status_code: u16, config: &Server
 }
 
 
-fn handle_get(_path: &str, config: &ServerConfig) -> (String, String) {
-    if _path.starts_with("/cgi-bin/") {
-        let script_path = format!("{}{}", config.root_dir, _path);
-        match Command::new("python3").arg(&script_path).output() {
-            Ok(output) => {
-                let body = String::from_utf8_lossy(&output.stdout).to_string();
-                return ("HTTP/1.1 200 OK\r\nContent-Type: text/plain".to_string(), body);
+const STREAM_CHUNK_SIZE: usize = 64 * 1024;
+
+fn handle_get(
+    stream: &mut TcpStream,
+    _path: &str,
+    headers: &HashMap<String, String>,
+    connection_header: &str,
+    config: &ServerConfig,
+) -> io::Result<()> {
+    let mut filename = match resolve_path(&config.root_dir, _path) {
+        Some(filename) => filename,
+        None => return write_403(stream, connection_header, config),
+    };
+
+    let mut metadata = match fs::metadata(&filename) {
+        Ok(metadata) => metadata,
+        Err(_) => return write_404(stream, connection_header, config),
+    };
+
+    if metadata.is_dir() {
+        let index_file = format!("{}/{}", filename, config.default_file);
+        match fs::metadata(&index_file) {
+            Ok(index_meta) if index_meta.is_file() => {
+                filename = index_file;
+                metadata = index_meta;
             }
-            Err(_) => return ("HTTP/1.1 500 Internal Server Error".to_string(), "CGI script failed".to_string()),
-        }
-    }
-    let mut filename = format!("{}/index.html", config.root_dir);
-    let mut content_type = "text/html";
-    if _path != "/" {
-        filename = format!("{}{}", config.root_dir, _path);
-        content_type = match _path.split('.').last() {
-            Some("css") => "text/css",
-            Some("js") => "application/javascript",
-            Some("png") => "image/png",
-            Some("jpg") | Some("jpeg") => "image/jpeg",
-            Some("webp") => "image/webp",
-            _ => "text/plain",
+            _ if config.autoindex => return write_autoindex(stream, &filename, _path, connection_header),
+            _ => return write_404(stream, connection_header, config),
+        }
+    }
+
+    let content_type = match filename.rsplit('.').next() {
+        Some("html") | Some("htm") => "text/html",
+        Some("css") => "text/css",
+        Some("js") => "application/javascript",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("webp") => "image/webp",
+        _ => "text/plain",
+    };
+
+    let total = metadata.len();
+    let mtime_secs = metadata.modified().map(http_date::unix_secs).unwrap_or(0);
+    let etag = format!("\"{:x}-{:x}\"", total, mtime_secs);
+    let last_modified = http_date::format_http_date(mtime_secs);
+    let validators = format!("ETag: {etag}\r\nLast-Modified: {last_modified}");
+
+    if is_not_modified(headers, &etag, mtime_secs) {
+        let status_line = format!("HTTP/1.1 304 Not Modified\r\n{validators}");
+        return write_buffered_response(stream, &status_line, connection_header, &[]);
+    }
+
+    let mut file = match fs::File::open(&filename) {
+        Ok(file) => file,
+        Err(_) => return write_404(stream, connection_header, config),
+    };
+
+    match headers.get("Range").map(|value| parse_range(value, total)) {
+        Some(Some(Err(()))) => {
+            let status_line = format!("HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */{total}");
+            write_buffered_response(stream, &status_line, connection_header, &[])
+        }
+        Some(Some(Ok((start, end)))) => {
+            let len = end - start + 1;
+            file.seek(SeekFrom::Start(start))?;
+            let status_line = format!(
+                "HTTP/1.1 206 Partial Content\r\nContent-Type: {content_type}\r\nContent-Range: bytes {start}-{end}/{total}\r\n{validators}"
+            );
+            write_response_header(stream, &status_line, connection_header, len)?;
+            stream_file_body(stream, &mut file, len)
+        }
+        _ => {
+            let status_line = format!("HTTP/1.1 200 OK\r\nContent-Type: {content_type}\r\n{validators}");
+            write_response_header(stream, &status_line, connection_header, total)?;
+            stream_file_body(stream, &mut file, total)
+        }
+    }
+}
+
+fn write_404(stream: &mut TcpStream, connection_header: &str, config: &ServerConfig) -> io::Result<()> {
+    let error_page = format!("{}/404.html", config.root_dir);
+    let body = fs::read_to_string(&error_page).unwrap_or_default();
+    write_buffered_response(stream, "HTTP/1.1 404 Not Found", connection_header, body.as_bytes())
+}
+
+fn write_500(stream: &mut TcpStream, connection_header: &str, message: &str) -> io::Result<()> {
+    write_buffered_response(stream, "HTTP/1.1 500 Internal Server Error", connection_header, message.as_bytes())
+}
+
+/// Runs a `/cgi-bin/` script per CGI/1.1: standard environment variables,
+/// the request body on stdin for POST, and the script's stdout parsed back
+/// into a header block (optionally a `Status:` line) followed by the body.
+fn handle_cgi(
+    stream: &mut TcpStream,
+    request: &Request,
+    connection_header: &str,
+    config: &ServerConfig,
+) -> io::Result<()> {
+    let script_path = match resolve_path(&config.root_dir, &request.path) {
+        Some(path) => path,
+        None => return write_403(stream, connection_header, config),
+    };
+
+    if fs::metadata(&script_path).is_err() {
+        return write_404(stream, connection_header, config);
+    }
+
+    let interpreter = match script_path
+        .rsplit('.')
+        .next()
+        .and_then(|ext| config.cgi_interpreters.get(ext))
+    {
+        Some(interpreter) => interpreter,
+        None => return write_500(stream, connection_header, "No CGI interpreter configured for this script"),
+    };
+
+    let remote_addr = stream.peer_addr().map(|addr| addr.ip().to_string()).unwrap_or_default();
+    let content_type = request.headers.get("Content-Type").cloned().unwrap_or_default();
+
+    let mut command = Command::new(interpreter);
+    command
+        .arg(&script_path)
+        .env("REQUEST_METHOD", &request.method)
+        .env("QUERY_STRING", &request.query)
+        .env("CONTENT_LENGTH", request.body.len().to_string())
+        .env("CONTENT_TYPE", content_type)
+        .env("PATH_INFO", &request.path)
+        .env("SCRIPT_NAME", &request.path)
+        .env("SERVER_PROTOCOL", &request.version)
+        .env("REMOTE_ADDR", remote_addr)
+        .stdin(if request.method == "POST" { Stdio::piped() } else { Stdio::null() })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+
+    for (key, value) in &request.headers {
+        if key.eq_ignore_ascii_case("Content-Length") || key.eq_ignore_ascii_case("Content-Type") {
+            continue;
+        }
+        command.env(format!("HTTP_{}", key.to_uppercase().replace('-', "_")), value);
+    }
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => return write_500(stream, connection_header, "Failed to start CGI script"),
+    };
+
+    // Write stdin from its own thread: the script may start emitting stdout
+    // before it has finished reading a large body, and writing inline here
+    // while `wait_with_output` hasn't started draining stdout yet would
+    // deadlock both sides against their pipe buffers.
+    let stdin_writer = if request.method == "POST" {
+        child.stdin.take().map(|mut stdin| {
+            let body = request.body.clone();
+            thread::spawn(move || stdin.write_all(body.as_bytes()))
+        })
+    } else {
+        None
+    };
+
+    let output = match child.wait_with_output() {
+        Ok(output) => output,
+        Err(_) => return write_500(stream, connection_header, "CGI script failed"),
+    };
+
+    if let Some(writer) = stdin_writer {
+        let _ = writer.join();
+    }
+
+    if !output.status.success() {
+        return write_500(stream, connection_header, "CGI script exited with an error");
+    }
+
+    match parse_cgi_output(&output.stdout) {
+        Some((status_line, headers, body)) => {
+            let mut header_block = status_line;
+            for (key, value) in headers {
+                header_block.push_str(&format!("\r\n{key}: {value}"));
+            }
+            write_buffered_response(stream, &header_block, connection_header, body)
+        }
+        None => write_500(stream, connection_header, "CGI script produced malformed headers"),
+    }
+}
+
+/// Splits raw CGI output into its header block and body, pulling a leading
+/// `Status:` line into an HTTP status line (defaulting to 200 OK).
+fn parse_cgi_output(raw: &[u8]) -> Option<(String, Vec<(String, String)>, &[u8])> {
+    let (header_len, separator_len) = find_header_separator(raw)?;
+    let header_text = std::str::from_utf8(&raw[..header_len]).ok()?;
+    let body = &raw[header_len + separator_len..];
+
+    let mut status_code: u16 = 200;
+    let mut status_text = String::new();
+    let mut headers = Vec::new();
+    let mut has_content_type = false;
+
+    for line in header_text.split('\n') {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line.split_once(':')?;
+        let key = key.trim();
+        let value = value.trim();
+
+        if key.eq_ignore_ascii_case("Status") {
+            let mut parts = value.splitn(2, ' ');
+            status_code = parts.next()?.parse().ok()?;
+            status_text = parts.next().unwrap_or("").to_string();
+        } else if key.eq_ignore_ascii_case("Content-Length") || key.eq_ignore_ascii_case("Connection") {
+            // Framing headers are the server's to set (write_buffered_response
+            // appends its own Content-Length; handle_connection appends its own
+            // Connection); a script-supplied one would otherwise be duplicated.
+            continue;
+        } else {
+            if key.eq_ignore_ascii_case("Content-Type") {
+                has_content_type = true;
+            }
+            headers.push((key.to_string(), value.to_string()));
+        }
+    }
+
+    if !has_content_type {
+        headers.push(("Content-Type".to_string(), "text/html".to_string()));
+    }
+
+    if status_text.trim().is_empty() {
+        status_text = default_reason_phrase(status_code).to_string();
+    }
+
+    Some((format!("HTTP/1.1 {status_code} {status_text}"), headers, body))
+}
+
+fn find_header_separator(raw: &[u8]) -> Option<(usize, usize)> {
+    for i in 0..raw.len() {
+        if raw[i..].starts_with(b"\r\n\r\n") {
+            return Some((i, 4));
+        }
+        if raw[i..].starts_with(b"\n\n") {
+            return Some((i, 2));
+        }
+    }
+    None
+}
+
+fn default_reason_phrase(status_code: u16) -> &'static str {
+    match status_code {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        _ => "",
+    }
+}
+
+fn write_403(stream: &mut TcpStream, connection_header: &str, config: &ServerConfig) -> io::Result<()> {
+    let body = config.error_pages.get(&403).cloned().unwrap_or_else(|| "Error 403".to_string());
+    write_buffered_response(stream, "HTTP/1.1 403 Forbidden", connection_header, body.as_bytes())
+}
+
+/// Renders a directory listing for `dir_path` (the resolved filesystem path)
+/// under `url_path` (the request path it was reached by). Entries that can't
+/// be read (permissions, races with deletion) are skipped rather than
+/// failing the whole listing.
+fn write_autoindex(
+    stream: &mut TcpStream,
+    dir_path: &str,
+    url_path: &str,
+    connection_header: &str,
+) -> io::Result<()> {
+    let mut entries: Vec<(String, bool)> = Vec::new();
+    if let Ok(read_dir) = fs::read_dir(dir_path) {
+        for entry in read_dir.flatten() {
+            let file_type = match entry.file_type() {
+                Ok(file_type) => file_type,
+                Err(_) => continue,
+            };
+            let name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(_) => continue,
+            };
+            entries.push((name, file_type.is_dir()));
+        }
+    }
+    entries.sort();
+
+    let base = if url_path.ends_with('/') {
+        url_path.to_string()
+    } else {
+        format!("{url_path}/")
+    };
+
+    let mut list_items = String::new();
+    if base != "/" {
+        let trimmed = base.trim_end_matches('/');
+        let parent_href = match trimmed.rfind('/') {
+            Some(idx) => trimmed[..=idx].to_string(),
+            None => "/".to_string(),
         };
+        list_items.push_str(&format!("<li><a href=\"{parent_href}\">../</a></li>\n"));
     }
-    match fs::read(&filename) {
-        Ok(contents) => {
-            let response_body = String::from_utf8_lossy(&contents).to_string();
-            (format!("HTTP/1.1 200 OK\r\nContent-Type: {content_type}"), response_body)
+    for (name, is_dir) in &entries {
+        let href = if *is_dir {
+            format!("{base}{}/", percent_encode(name))
+        } else {
+            format!("{base}{}", percent_encode(name))
+        };
+        let display = if *is_dir { format!("{name}/") } else { name.clone() };
+        list_items.push_str(&format!("<li><a href=\"{href}\">{}</a></li>\n", html_escape(&display)));
+    }
+
+    let title = html_escape(&base);
+    let body = format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>Index of {title}</title></head>\n<body>\n<h1>Index of {title}</h1>\n<ul>\n{list_items}</ul>\n</body>\n</html>\n"
+    );
+
+    write_buffered_response(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8",
+        connection_header,
+        body.as_bytes(),
+    )
+}
+
+fn percent_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => out.push(byte as char),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn html_escape(input: &str) -> String {
+    input
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Resolves a decoded request path against `root_dir`, collapsing `.`/`..`
+/// segments logically so `..` can never walk the result above `root_dir`
+/// (`None` means it tried to), then canonicalizes the deepest existing
+/// ancestor and checks it still lives under `root_dir` on the filesystem —
+/// lexical collapsing alone lets a symlink inside `root_dir` walk back out.
+/// `/` (or any path with no segments) maps to `root_dir` itself, which
+/// `handle_get` then resolves to a directory index. Non-existent trailing
+/// components (a file that doesn't exist yet) are kept as-is so callers can
+/// still report a 404 instead of a 403.
+fn resolve_path(root_dir: &str, request_path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = Vec::new();
+    for part in request_path.split('/') {
+        match part {
+            "" | "." => continue,
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
         }
-        Err(_) => {
-            let error_page = format!("{}/404.html", config.root_dir);
-            let response_body = fs::read_to_string(&error_page).unwrap_or_default();
-            ("HTTP/1.1 404 Not Found".to_string(), response_body)
+    }
+
+    let lexical_path = if segments.is_empty() {
+        root_dir.to_string()
+    } else {
+        format!("{}/{}", root_dir, segments.join("/"))
+    };
+
+    let canonical_root = fs::canonicalize(root_dir).ok()?;
+
+    let mut current = Path::new(&lexical_path);
+    let mut trailing: Vec<&std::ffi::OsStr> = Vec::new();
+    loop {
+        match fs::canonicalize(current) {
+            Ok(canonical) => {
+                if !canonical.starts_with(&canonical_root) {
+                    return None;
+                }
+                let mut resolved = canonical;
+                for name in trailing.into_iter().rev() {
+                    resolved.push(name);
+                }
+                return Some(resolved.to_string_lossy().into_owned());
+            }
+            Err(_) => {
+                trailing.push(current.file_name()?);
+                current = current.parent()?;
+            }
         }
     }
 }
 
+fn write_response_header(
+    stream: &mut TcpStream,
+    status_line: &str,
+    connection_header: &str,
+    content_length: u64,
+) -> io::Result<()> {
+    let header = format!("{status_line}\r\n{connection_header}\r\nContent-Length: {content_length}\r\n\r\n");
+    stream.write_all(header.as_bytes())
+}
+
+fn write_buffered_response(
+    stream: &mut TcpStream,
+    status_line: &str,
+    connection_header: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write_response_header(stream, status_line, connection_header, body.len() as u64)?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Streams `remaining` bytes from `file`'s current position in fixed-size
+/// blocks so large downloads don't get buffered into memory whole.
+fn stream_file_body(stream: &mut TcpStream, file: &mut fs::File, mut remaining: u64) -> io::Result<()> {
+    let mut buffer = [0u8; STREAM_CHUNK_SIZE];
+    while remaining > 0 {
+        let to_read = remaining.min(STREAM_CHUNK_SIZE as u64) as usize;
+        let read = file.read(&mut buffer[..to_read])?;
+        if read == 0 {
+            break;
+        }
+        stream.write_all(&buffer[..read])?;
+        remaining -= read as u64;
+    }
+    stream.flush()
+}
+
+/// Parses a single-range `Range: bytes=...` header against the file size.
+/// `None` means the header wasn't in a form we understand (serve the whole
+/// file, per RFC 7233 §3.1); `Some(Err(()))` means it parsed but is out of
+/// bounds (416); `Some(Ok((start, end)))` is the inclusive byte range to serve.
+fn parse_range(range_header: &str, total: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = range_header.strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start_str, end_str) = spec.split_once('-')?;
+
+    if start_str.is_empty() {
+        let suffix: u64 = end_str.parse().ok()?;
+        return Some(if suffix == 0 || total == 0 {
+            Err(())
+        } else {
+            Ok((total.saturating_sub(suffix), total - 1))
+        });
+    }
+
+    let start: u64 = start_str.parse().ok()?;
+    let end: u64 = if end_str.is_empty() {
+        total.saturating_sub(1)
+    } else {
+        match end_str.parse() {
+            Ok(end) => end,
+            Err(_) => return None,
+        }
+    };
+
+    if start >= total || start > end {
+        return Some(Err(()));
+    }
+
+    Some(Ok((start, end.min(total - 1))))
+}
+
+/// `If-None-Match` wins outright when present; `If-Modified-Since` is only
+/// consulted as a fallback, per RFC 7232 §6.
+fn is_not_modified(headers: &HashMap<String, String>, etag: &str, mtime_secs: u64) -> bool {
+    if let Some(inm) = headers.get("If-None-Match") {
+        return inm
+            .split(',')
+            .any(|candidate| candidate.trim().trim_start_matches("W/") == etag);
+    }
+
+    if let Some(ims) = headers.get("If-Modified-Since") {
+        if let Some(since) = http_date::parse_http_date(ims.trim()) {
+            return mtime_secs <= since;
+        }
+    }
+
+    false
+}
+
 fn handle_post(_path: &str, body: &str, config: &ServerConfig) -> (String, String) {
     if body.len() > config.max_body_size {
         return ("HTTP/1.1 413 Payload Too Large".to_string(), "Request body too large".to_string());
@@ -148,6 +667,12 @@ fn main() -> io::Result<()> {
             max_body_size: 1024 * 1024, 
             allowed_methods: vec!["GET".to_string(), "POST".to_string(), "DELETE".to_string()],
             default_file: "index.html".to_string(),
+            keep_alive_timeout: Duration::from_secs(5),
+            autoindex: false,
+            cgi_interpreters: [
+                ("py".to_string(), "python3".to_string()),
+                ("php".to_string(), "php-cgi".to_string()),
+            ].iter().cloned().collect(),
         },
     ];
 
@@ -173,4 +698,58 @@ fn main() -> io::Result<()> {
         }
         thread::sleep(Duration::from_millis(100));
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range_handles_start_and_end() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+    }
+
+    #[test]
+    fn parse_range_handles_open_ended_start() {
+        assert_eq!(parse_range("bytes=900-", 1000), Some(Ok((900, 999))));
+    }
+
+    #[test]
+    fn parse_range_handles_suffix_length() {
+        assert_eq!(parse_range("bytes=-500", 1000), Some(Ok((500, 999))));
+    }
+
+    #[test]
+    fn parse_range_rejects_out_of_bounds_start() {
+        assert_eq!(parse_range("bytes=1000-1001", 1000), Some(Err(())));
+    }
+
+    #[test]
+    fn parse_range_ignores_multi_range_and_unrecognized_forms() {
+        assert_eq!(parse_range("bytes=0-10,20-30", 1000), None);
+        assert_eq!(parse_range("bogus", 1000), None);
+    }
+
+    #[test]
+    fn parse_cgi_output_reads_status_and_headers() {
+        let raw = b"Status: 201 Created\r\nContent-Type: text/plain\r\nX-Foo: bar\r\n\r\nhello";
+        let (status_line, headers, body) = parse_cgi_output(raw).unwrap();
+        assert_eq!(status_line, "HTTP/1.1 201 Created");
+        assert!(headers.contains(&("X-Foo".to_string(), "bar".to_string())));
+        assert_eq!(body, b"hello");
+    }
+
+    #[test]
+    fn parse_cgi_output_defaults_to_200_and_adds_content_type() {
+        let raw = b"X-Foo: bar\n\nbody text";
+        let (status_line, headers, body) = parse_cgi_output(raw).unwrap();
+        assert_eq!(status_line, "HTTP/1.1 200 OK");
+        assert!(headers.contains(&("Content-Type".to_string(), "text/html".to_string())));
+        assert_eq!(body, b"body text");
+    }
+
+    #[test]
+    fn parse_cgi_output_rejects_missing_header_separator() {
+        assert!(parse_cgi_output(b"no separator here").is_none());
+    }
 }
\ No newline at end of file
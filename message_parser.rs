@@ -1,35 +1,152 @@
-use std::{collections::HashMap, io::{BufReader, BufRead,  Read}};
+use std::{collections::HashMap, io::{BufReader, BufRead, ErrorKind, Read}};
 use std::net::TcpStream;
 
 #[derive(Debug)]
 pub struct Request {
     pub method: String,
+    /// Percent-decoded, e.g. `/My Docs/report.pdf`. Never contains a `?query`.
     pub path: String,
+    /// Everything after the `?`, still percent-encoded (CGI's `QUERY_STRING` wants it raw).
+    pub query: String,
+    pub version: String,
     pub headers: HashMap<String, String>,
     pub body: String,
 }
 
+/// Why these variants: `handle_connection` needs to tell a client that closed
+/// the socket between keep-alive requests (closed quietly) apart from one that
+/// went idle or stalled past the read timeout (408) or sent garbage (400).
+#[derive(Debug)]
+pub enum ParseError {
+    ConnectionClosed,
+    Timeout,
+    Malformed(String),
+    PayloadTooLarge,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::ConnectionClosed => write!(f, "connection closed by peer"),
+            ParseError::Timeout => write!(f, "timed out waiting for request"),
+            ParseError::Malformed(msg) => write!(f, "{msg}"),
+            ParseError::PayloadTooLarge => write!(f, "request body exceeds max_body_size"),
+        }
+    }
+}
+
+fn map_io_err(e: std::io::Error) -> ParseError {
+    match e.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => ParseError::Timeout,
+        _ => ParseError::Malformed(e.to_string()),
+    }
+}
+
+/// Decodes `%XX` escapes in a request-target path component. Leaves `+` alone
+/// since it's only a space in query strings, not paths.
+fn percent_decode(input: &str) -> Result<String, ParseError> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = input
+                .get(i + 1..i + 3)
+                .ok_or_else(|| ParseError::Malformed("Invalid percent-encoding in path".to_string()))?;
+            let byte = u8::from_str_radix(hex, 16)
+                .map_err(|_| ParseError::Malformed("Invalid percent-encoding in path".to_string()))?;
+            out.push(byte);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out).map_err(|_| ParseError::Malformed("Path is not valid UTF-8 after decoding".to_string()))
+}
+
+/// Decodes a `Transfer-Encoding: chunked` body: repeated `SIZE\r\nDATA\r\n`
+/// chunks (an optional `;extension` after `SIZE` is ignored) terminated by a
+/// zero-size chunk and, per RFC 9112 §7.1.2, an optional block of trailer
+/// header lines before the final blank line.
+fn read_chunked_body<R: BufRead>(buf_reader: &mut R, max_body_size: usize) -> Result<String, ParseError> {
+    let mut body = Vec::new();
+
+    loop {
+        let mut size_line = String::new();
+        let bytes_read = buf_reader.read_line(&mut size_line).map_err(map_io_err)?;
+        if bytes_read == 0 {
+            return Err(ParseError::ConnectionClosed);
+        }
+
+        let size_str = size_line.trim_end().split(';').next().unwrap_or("").trim();
+        let chunk_size = usize::from_str_radix(size_str, 16)
+            .map_err(|_| ParseError::Malformed("Invalid chunk size".to_string()))?;
+
+        if chunk_size == 0 {
+            loop {
+                let mut trailer_line = String::new();
+                let bytes_read = buf_reader.read_line(&mut trailer_line).map_err(map_io_err)?;
+                if bytes_read == 0 {
+                    return Err(ParseError::ConnectionClosed);
+                }
+                if trailer_line.trim_end().is_empty() {
+                    break;
+                }
+            }
+            break;
+        }
+
+        if body.len().checked_add(chunk_size).is_none_or(|sum| sum > max_body_size) {
+            return Err(ParseError::PayloadTooLarge);
+        }
+
+        let mut chunk = vec![0; chunk_size];
+        buf_reader.read_exact(&mut chunk).map_err(map_io_err)?;
+        body.extend_from_slice(&chunk);
+
+        let mut crlf = [0; 2];
+        buf_reader.read_exact(&mut crlf).map_err(map_io_err)?;
+        if &crlf != b"\r\n" {
+            return Err(ParseError::Malformed("Missing chunk terminator".to_string()));
+        }
+    }
+
+    Ok(String::from_utf8_lossy(&body).to_string())
+}
+
 pub struct MessageParser;
 
 impl MessageParser {
-    pub fn parse_request(stream: &mut TcpStream, max_body_size: usize) -> Result<Request, String> {
+    pub fn parse_request(stream: &mut TcpStream, max_body_size: usize) -> Result<Request, ParseError> {
         let mut buf_reader = BufReader::new(stream);
         let mut request_line = String::new();
-        
-        buf_reader.read_line(&mut request_line).map_err(|e| e.to_string())?;
+
+        let bytes_read = buf_reader.read_line(&mut request_line).map_err(map_io_err)?;
+        if bytes_read == 0 {
+            return Err(ParseError::ConnectionClosed);
+        }
+
         let parts: Vec<&str> = request_line.trim().split_whitespace().collect();
-        
+
         if parts.len() < 3 {
-            return Err("Invalid request line".to_string());
+            return Err(ParseError::Malformed("Invalid request line".to_string()));
         }
 
         let method = parts[0].to_string();
-        let path = parts[1].to_string();
+        let (raw_path, query) = match parts[1].split_once('?') {
+            Some((path, query)) => (path, query.to_string()),
+            None => (parts[1], String::new()),
+        };
+        let path = percent_decode(raw_path)?;
+        let version = parts[2].to_string();
 
         let mut headers = HashMap::new();
         let mut content_length = 0;
         for line in buf_reader.by_ref().lines() {
-            let line = line.map_err(|e| e.to_string())?;
+            let line = line.map_err(map_io_err)?;
             if line.is_empty() {
                 break;
             }
@@ -42,22 +159,82 @@ impl MessageParser {
             }
         }
 
-        if content_length > max_body_size {
-            return Err("Payload Too Large".to_string());
-        }
+        let chunked = headers
+            .get("Transfer-Encoding")
+            .map(|value| {
+                value
+                    .split(',')
+                    .next_back()
+                    .unwrap_or("")
+                    .trim()
+                    .eq_ignore_ascii_case("chunked")
+            })
+            .unwrap_or(false);
 
-        let mut body = String::new();
-        if content_length > 0 {
-            let mut buffer = vec![0; content_length];
-            buf_reader.read_exact(&mut buffer).map_err(|e| e.to_string())?;
-            body = String::from_utf8_lossy(&buffer).to_string();
-        }
+        let body = if chunked {
+            read_chunked_body(&mut buf_reader, max_body_size)?
+        } else {
+            if content_length > max_body_size {
+                return Err(ParseError::PayloadTooLarge);
+            }
+
+            let mut body = String::new();
+            if content_length > 0 {
+                let mut buffer = vec![0; content_length];
+                buf_reader.read_exact(&mut buffer).map_err(map_io_err)?;
+                body = String::from_utf8_lossy(&buffer).to_string();
+            }
+            body
+        };
 
         Ok(Request {
             method,
             path,
+            query,
+            version,
             headers,
             body,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn percent_decode_handles_escapes_and_leaves_plus_alone() {
+        assert_eq!(percent_decode("My%20Docs/a+b").unwrap(), "My Docs/a+b");
+    }
+
+    #[test]
+    fn percent_decode_rejects_truncated_escape() {
+        assert!(matches!(percent_decode("bad%2"), Err(ParseError::Malformed(_))));
+    }
+
+    #[test]
+    fn read_chunked_body_joins_chunks_and_skips_trailers() {
+        let mut reader = Cursor::new(b"5\r\nhello\r\n6\r\n world\r\n0\r\nX-Trailer: ignored\r\n\r\n".to_vec());
+        let body = read_chunked_body(&mut reader, 1024).unwrap();
+        assert_eq!(body, "hello world");
+    }
+
+    #[test]
+    fn read_chunked_body_enforces_max_body_size() {
+        let mut reader = Cursor::new(b"5\r\nhello\r\n0\r\n\r\n".to_vec());
+        assert!(matches!(read_chunked_body(&mut reader, 3), Err(ParseError::PayloadTooLarge)));
+    }
+
+    #[test]
+    fn read_chunked_body_rejects_invalid_chunk_size() {
+        let mut reader = Cursor::new(b"zz\r\nhello\r\n0\r\n\r\n".to_vec());
+        assert!(matches!(read_chunked_body(&mut reader, 1024), Err(ParseError::Malformed(_))));
+    }
+
+    #[test]
+    fn read_chunked_body_rejects_missing_terminator() {
+        let mut reader = Cursor::new(b"5\r\nhelloXX0\r\n\r\n".to_vec());
+        assert!(matches!(read_chunked_body(&mut reader, 1024), Err(ParseError::Malformed(_))));
+    }
+}